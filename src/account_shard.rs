@@ -1,5 +1,6 @@
 //! Account-backed helpers for shards: PDA derivation and account sizing helpers.
 
+use crate::shard::JOURNAL_CAPACITY;
 use crate::shard::MappingShard;
 use crate::traits::{ShardKey, ShardValue};
 use anchor_lang::prelude::*;
@@ -14,15 +15,29 @@ pub fn derive_shard_pda(program_id: &Pubkey, shard_index: u8) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[SHARD_SEED_PREFIX, &[shard_index]], program_id)
 }
 
+/// Fixed-size bytes contributed by `MappingShard`'s non-`items` scalar
+/// fields: `shard_id` (1), `canary_start`/`canary_end` (8 each),
+/// `item_count`/`max_items` (2 each), `eviction_enabled` (1), `next_tick`
+/// (4), `journal_enabled` (1), `readonly` (1).
+const SHARD_FIXED_FIELDS_SIZE: usize = 1 + 8 + 8 + 2 + 2 + 1 + 4 + 1 + 1;
+/// Borsh length-prefix overhead for each of `items`, `access_ticks`, and `journal`.
+const VEC_LEN_PREFIX_SIZE: usize = 4;
+/// Worst-case serialized size of one `JournalEntry`: a 1-byte variant tag plus its `u16` payload.
+const JOURNAL_ENTRY_SIZE: usize = 1 + 2;
+
 /// Conservative estimate of required account space for a MappingShard<K, V>.
 /// This is only an estimate: for production, compute accurate size for the concrete K/V types.
 /// - 8 bytes: account discriminator
-/// - Anchor/Borsh overhead for vec length and entries is included approximately
+/// - `SHARD_FIXED_FIELDS_SIZE` bytes for the shard's scalar fields
+/// - `items`: length prefix plus `max_items` entries
+/// - `access_ticks`: length prefix plus one `u32` per item (eviction mode keeps it parallel to `items`)
+/// - `journal`: length prefix plus up to `JOURNAL_CAPACITY` entries (bounded regardless of `max_items`)
 pub fn estimate_shard_account_size(k_size: usize, v_size: usize, max_items: usize) -> usize {
-    // 8: account discriminator
-    // 4: vector length prefix (borsh)
-    // each item: k_size + v_size + 4 (for possible length prefixes for variable types)
-    8 + 4 + (max_items * (k_size + v_size + 4)) + 32
+    let items_size = VEC_LEN_PREFIX_SIZE + (max_items * (k_size + v_size + 4));
+    let access_ticks_size = VEC_LEN_PREFIX_SIZE + (max_items * 4);
+    let journal_size = VEC_LEN_PREFIX_SIZE + (JOURNAL_CAPACITY * JOURNAL_ENTRY_SIZE);
+
+    8 + SHARD_FIXED_FIELDS_SIZE + items_size + access_ticks_size + journal_size + 32
 }
 
 /// Wrapper struct you can use *in your program* for a concrete shard account.
@@ -0,0 +1,160 @@
+//! Cross-shard compaction: repack the live entries of sparse shards into
+//! the minimum number of shards, so the emptied-out shards can have their
+//! accounts closed and their rent reclaimed.
+
+use crate::shard::MappingShard;
+use crate::traits::{ShardKey, ShardValue, ShardedMap};
+use std::mem::size_of;
+
+/// Snapshot of a shard's occupancy, used to decide compaction candidates.
+#[derive(Clone, Debug)]
+pub struct ShardAliveInfo {
+    pub shard_id: u8,
+    pub len: usize,
+    pub max_items: usize,
+    pub load_factor: f32,
+}
+
+/// Compute alive info for every shard in `shards`, without mutating them.
+pub fn shard_alive_infos<K: ShardKey, V: ShardValue>(
+    shards: &[MappingShard<K, V>],
+) -> Vec<ShardAliveInfo> {
+    shards
+        .iter()
+        .map(|shard| ShardAliveInfo {
+            shard_id: shard.shard_id,
+            len: shard.len(),
+            max_items: shard.max_capacity(),
+            load_factor: shard.load_factor(),
+        })
+        .collect()
+}
+
+/// Tuning knobs for a compaction pass.
+#[derive(Clone, Copy, Debug)]
+pub struct CompactionTuning {
+    /// Shards with a load factor below this ratio are compaction candidates.
+    pub shrink_ratio: f32,
+    /// Target item count to fill each destination shard to before opening the next one.
+    pub ideal_items: usize,
+    /// Maximum number of destination shards a single pass may pack into.
+    pub max_shards: usize,
+}
+
+/// Summary of what a compaction pass did.
+#[derive(Clone, Debug, Default)]
+pub struct CompactionReport<K> {
+    /// Shard ids that ended up fully empty and are eligible for account closure.
+    pub emptied_shard_ids: Vec<u8>,
+    /// `(key, from_shard_id, to_shard_id)` for every entry that actually moved.
+    pub moved_keys: Vec<(K, u8, u8)>,
+    /// Total entries relocated during the pass.
+    pub items_moved: usize,
+    /// Item-capacity slots reclaimed, summed over the emptied shards.
+    pub slots_reclaimed: usize,
+    /// Conservative estimate of account bytes reclaimed, based on `size_of::<K>() + size_of::<V>()`.
+    pub bytes_reclaimed: usize,
+}
+
+/// Repack the live entries of sparse shards (load factor below
+/// `tuning.shrink_ratio`) into as few of those same shards as possible.
+/// Destinations are filled up to `tuning.ideal_items` before the next one
+/// is opened, and at most `tuning.max_shards` destinations are used. Any
+/// entry that can't be placed under that cap is left in its original
+/// shard rather than dropped.
+///
+/// Readonly shards (frozen for migration, see chunk0-5) are never touched
+/// as either a source or a destination. Every mutation is routed through
+/// `clear()`/`insert()` rather than poking `items` directly, so
+/// `access_ticks` and the operation journal stay in sync for
+/// eviction/diagnostics-enabled shards.
+pub fn compact<K, V>(
+    shards: &mut [MappingShard<K, V>],
+    tuning: &CompactionTuning,
+) -> CompactionReport<K>
+where
+    K: ShardKey,
+    V: ShardValue,
+{
+    let mut report = CompactionReport {
+        emptied_shard_ids: Vec::new(),
+        moved_keys: Vec::new(),
+        items_moved: 0,
+        slots_reclaimed: 0,
+        bytes_reclaimed: 0,
+    };
+
+    if tuning.max_shards == 0 {
+        return report;
+    }
+
+    let candidate_indices: Vec<usize> = shards
+        .iter()
+        .enumerate()
+        .filter(|(_, shard)| {
+            !shard.readonly
+                && shard.verify_integrity().is_ok()
+                && shard.load_factor() < tuning.shrink_ratio
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    if candidate_indices.is_empty() {
+        return report;
+    }
+
+    // Snapshot every candidate shard's live entries, then drain each one
+    // through `clear()` — not a raw `items.drain(..)` — so access_ticks
+    // and the journal stay consistent. Readonly/corrupted shards were
+    // already excluded above, so `clear()` can't fail here.
+    let mut pool: Vec<(usize, K, V)> = Vec::new();
+    for &idx in &candidate_indices {
+        pool.extend(shards[idx].items.clone().into_iter().map(|(k, v)| (idx, k, v)));
+        shards[idx].clear().ok();
+    }
+
+    let max_destinations = tuning.max_shards.min(candidate_indices.len());
+    let mut dest_cursor = 0;
+
+    for (source_idx, key, value) in pool {
+        let mut placed = false;
+        while dest_cursor < max_destinations {
+            let dest_idx = candidate_indices[dest_cursor];
+            let below_ideal = shards[dest_idx].len() < tuning.ideal_items;
+            let fits = shards[dest_idx].can_insert_batch(&[(key.clone(), value.clone())]);
+
+            if below_ideal && fits && shards[dest_idx].insert(key.clone(), value.clone()).is_ok() {
+                if dest_idx != source_idx {
+                    let from_shard_id = shards[source_idx].shard_id;
+                    let to_shard_id = shards[dest_idx].shard_id;
+                    report.moved_keys.push((key, from_shard_id, to_shard_id));
+                    report.items_moved += 1;
+                }
+                placed = true;
+                break;
+            }
+
+            // Either this destination is full/doesn't fit, or the insert
+            // itself failed (e.g. a corrupted destination) — move on to
+            // the next one instead of treating it as placed.
+            dest_cursor += 1;
+        }
+
+        if !placed {
+            // No destination had room under `max_shards`; leave the entry
+            // where it started instead of dropping it.
+            shards[source_idx].insert(key, value).ok();
+        }
+    }
+
+    let emptied_indices: Vec<usize> = candidate_indices
+        .into_iter()
+        .filter(|&idx| shards[idx].is_empty())
+        .collect();
+
+    report.emptied_shard_ids = emptied_indices.iter().map(|&idx| shards[idx].shard_id).collect();
+    report.slots_reclaimed = emptied_indices.iter().map(|&idx| shards[idx].max_capacity()).sum();
+    report.bytes_reclaimed = report.slots_reclaimed * (size_of::<K>() + size_of::<V>());
+
+    report
+}
@@ -10,4 +10,10 @@ pub enum ShardError {
     InvalidShard,
     #[msg("Invalid capacity: new capacity cannot be smaller than current item count")]
     InvalidCapacity,
+    #[msg("Shard data is corrupted: canary or item_count mismatch.")]
+    Corrupted,
+    #[msg("Shard is read-only.")]
+    ReadOnly,
+    #[msg("Allocation failed: requested capacity could not be reserved.")]
+    AllocationFailed,
 }
@@ -3,14 +3,22 @@
 //! Core public exports for the crate.
 
 pub mod account_shard;
+pub mod compaction;
 pub mod errors;
 pub mod shard;
+pub mod shard_format;
+pub mod shard_map;
+pub mod sorted_shard;
 pub mod traits;
 
 #[cfg(test)]
 mod tests;
 
 pub use account_shard::*;
+pub use compaction::*;
 pub use errors::*;
 pub use shard::*;
+pub use shard_format::*;
+pub use shard_map::*;
+pub use sorted_shard::*;
 pub use traits::*;
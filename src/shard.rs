@@ -3,6 +3,27 @@ use crate::traits::{ShardKey, ShardValue, ShardedMap};
 use anchor_lang::prelude::*;
 use std::marker::PhantomData;
 
+/// Sentinel word stored immediately before the `items` buffer in the
+/// serialized layout. `verify_integrity` checks this wasn't clobbered by
+/// an out-of-bounds write.
+pub const SHARD_CANARY_START: u64 = 0xDEAD_BEEF_CAFE_BABE;
+/// Sentinel word stored immediately after the `items` buffer in the
+/// serialized layout. See `SHARD_CANARY_START`.
+pub const SHARD_CANARY_END: u64 = 0xFEED_FACE_CAFE_F00D;
+/// Maximum number of entries kept in a shard's operation journal; older
+/// entries are dropped to make room for new ones.
+pub const JOURNAL_CAPACITY: usize = 16;
+
+/// A single recorded mutation, for post-mortem debugging of on-chain
+/// state. `Insert`/`Remove` carry the position within `items` the
+/// mutation touched; `Clear` carries the number of items cleared.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum JournalEntry {
+    Insert(u16),
+    Remove(u16),
+    Clear(u16),
+}
+
 #[derive(Debug, Clone)]
 pub struct CapacityStats {
     pub current_items: usize,
@@ -20,12 +41,36 @@ pub struct CapacityStats {
 pub struct MappingShard<K: ShardKey, V: ShardValue> {
     /// Optional shard id for debugging. If you use PDAs, you can omit storing this.
     pub shard_id: u8,
+    /// Canary word immediately before `items` in the serialized layout.
+    /// Should always equal `SHARD_CANARY_START`.
+    pub canary_start: u64,
     /// Bounded list of key-value pairs.
     pub items: Vec<(K, V)>,
+    /// Canary word immediately after `items` in the serialized layout.
+    /// Should always equal `SHARD_CANARY_END`.
+    pub canary_end: u64,
     /// cached count (kept as u16 to reduce serialized size)
     pub item_count: u16,
     /// Maximum allowed items in this shard (helps sizing accounts)
     pub max_items: u16,
+    /// When true, `insert_evicting` evicts the least-recently-used entry
+    /// on a full shard instead of erroring; plain `insert`/`remove` keep
+    /// `access_ticks` in sync either way.
+    pub eviction_enabled: bool,
+    /// Parallel recency tracker: `access_ticks[i]` corresponds to
+    /// `items[i]`. Higher values were accessed more recently.
+    pub access_ticks: Vec<u32>,
+    /// Monotonically increasing counter used to stamp `access_ticks`.
+    pub next_tick: u32,
+    /// When true, mutations are recorded into `journal` (bounded to
+    /// `JOURNAL_CAPACITY`) for post-mortem debugging.
+    pub journal_enabled: bool,
+    /// Bounded journal of recent mutations, oldest first.
+    pub journal: Vec<JournalEntry>,
+    /// When true, `insert`/`remove`/`clear`/`insert_evicting` return an
+    /// error instead of mutating — useful for freezing a shard during
+    /// migration.
+    pub readonly: bool,
     /// Phantom type marker so we can keep K,V generic
     _marker: PhantomData<(K, V)>,
 }
@@ -36,13 +81,199 @@ impl<K: ShardKey, V: ShardValue> MappingShard<K, V> {
         let cap = max_items as usize;
         Self {
             shard_id,
+            canary_start: SHARD_CANARY_START,
             items: Vec::with_capacity(cap),
+            canary_end: SHARD_CANARY_END,
             item_count: 0,
             max_items,
+            eviction_enabled: false,
+            access_ticks: Vec::new(),
+            next_tick: 0,
+            journal_enabled: false,
+            journal: Vec::new(),
+            readonly: false,
             _marker: PhantomData,
         }
     }
 
+    /// Fallible counterpart to `new`. Uses `Vec::try_reserve` so an
+    /// oversized `max_items` on the constrained BPF heap returns a clean
+    /// `ShardError::AllocationFailed` instead of aborting the program.
+    pub fn try_new(shard_id: u8, max_items: u16) -> Result<Self> {
+        let mut items = Vec::new();
+        items
+            .try_reserve(max_items as usize)
+            .map_err(|_| error!(ShardError::AllocationFailed))?;
+
+        Ok(Self {
+            shard_id,
+            canary_start: SHARD_CANARY_START,
+            items,
+            canary_end: SHARD_CANARY_END,
+            item_count: 0,
+            max_items,
+            eviction_enabled: false,
+            access_ticks: Vec::new(),
+            next_tick: 0,
+            journal_enabled: false,
+            journal: Vec::new(),
+            readonly: false,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Fallible counterpart to `resize_capacity`. Uses `Vec::try_reserve`
+    /// so an oversized `new_max_items` returns `ShardError::AllocationFailed`
+    /// instead of aborting the program.
+    pub fn try_resize_capacity(&mut self, new_max_items: u16) -> Result<()> {
+        if (new_max_items as usize) < self.items.len() {
+            return err!(ShardError::InvalidCapacity);
+        }
+
+        if new_max_items as usize > self.items.capacity() {
+            self.items
+                .try_reserve(new_max_items as usize - self.items.len())
+                .map_err(|_| error!(ShardError::AllocationFailed))?;
+        }
+
+        self.max_items = new_max_items;
+        Ok(())
+    }
+
+    /// Create a new shard with operation journaling enabled: recent
+    /// mutations are recorded into `journal` (bounded to `JOURNAL_CAPACITY`).
+    pub fn new_with_diagnostics(shard_id: u8, max_items: u16) -> Self {
+        let mut shard = Self::new(shard_id, max_items);
+        shard.journal_enabled = true;
+        shard
+    }
+
+    /// Record `entry` into the journal, evicting the oldest entry once
+    /// `JOURNAL_CAPACITY` is reached. No-op unless `journal_enabled`.
+    fn push_journal(&mut self, entry: JournalEntry) {
+        if !self.journal_enabled {
+            return;
+        }
+        if self.journal.len() >= JOURNAL_CAPACITY {
+            self.journal.remove(0);
+        }
+        self.journal.push(entry);
+    }
+
+    /// Validate that the canary words weren't clobbered by an
+    /// out-of-bounds write and that `item_count` matches `items.len()`.
+    pub fn verify_integrity(&self) -> Result<()> {
+        if self.canary_start != SHARD_CANARY_START || self.canary_end != SHARD_CANARY_END {
+            return err!(ShardError::Corrupted);
+        }
+        if self.item_count as usize != self.items.len() {
+            return err!(ShardError::Corrupted);
+        }
+        Ok(())
+    }
+
+    /// Look up `key` like `get`, but surface corruption as an error
+    /// instead of silently treating the shard as empty. Prefer this over
+    /// the `ShardedMap::get` trait method wherever the caller can handle
+    /// a `Result`.
+    pub fn get_checked(&self, key: &K) -> Result<Option<V>> {
+        self.verify_integrity()?;
+        Ok(self
+            .items
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone()))
+    }
+
+    /// Create a new shard with LRU eviction mode enabled: once full,
+    /// `insert_evicting` drops the least-recently-used entry instead of
+    /// returning `ShardError::ShardFull`.
+    pub fn new_with_eviction(shard_id: u8, max_items: u16) -> Self {
+        let mut shard = Self::new(shard_id, max_items);
+        shard.eviction_enabled = true;
+        shard.access_ticks = Vec::with_capacity(max_items as usize);
+        shard
+    }
+
+    /// Stamp `items[idx]` as the most recently used entry, when eviction
+    /// mode is enabled.
+    fn touch(&mut self, idx: usize) {
+        if !self.eviction_enabled {
+            return;
+        }
+        if let Some(tick) = self.access_ticks.get_mut(idx) {
+            *tick = self.next_tick;
+        }
+        self.next_tick = self.next_tick.wrapping_add(1);
+    }
+
+    /// Look up `key` like `get`, but also bump its recency so it isn't
+    /// picked for eviction next. Use this instead of the `ShardedMap::get`
+    /// trait method in eviction-mode shards, since that method takes
+    /// `&self` and can't update the recency tracker. Returns `None`,
+    /// without touching recency, if the shard fails its integrity check.
+    pub fn get_touch(&mut self, key: &K) -> Option<V> {
+        if self.verify_integrity().is_err() {
+            return None;
+        }
+        let pos = self.items.iter().position(|(k, _)| k == key)?;
+        self.touch(pos);
+        Some(self.items[pos].1.clone())
+    }
+
+    /// Insert `key`/`value`, evicting the least-recently-used entry if the
+    /// shard is full and eviction mode is enabled. Returns the evicted
+    /// pair, if any, so the caller can persist or account for it.
+    /// Behaves exactly like `insert` (including error on a full,
+    /// non-evicting shard) when no eviction is needed.
+    pub fn insert_evicting(&mut self, key: K, value: V) -> Result<Option<(K, V)>> {
+        self.verify_integrity()?;
+        if self.readonly {
+            return err!(ShardError::ReadOnly);
+        }
+
+        if let Some(pos) = self.items.iter().position(|(k, _)| *k == key) {
+            self.items[pos].1 = value;
+            self.touch(pos);
+            self.push_journal(JournalEntry::Insert(pos as u16));
+            return Ok(None);
+        }
+
+        if self.can_add_item() {
+            self.items.push((key, value));
+            self.item_count = self.items.len() as u16;
+            if self.eviction_enabled {
+                self.access_ticks.push(self.next_tick);
+                self.next_tick = self.next_tick.wrapping_add(1);
+            }
+            self.push_journal(JournalEntry::Insert((self.items.len() - 1) as u16));
+            return Ok(None);
+        }
+
+        if !self.eviction_enabled {
+            return err!(ShardError::ShardFull);
+        }
+
+        let evict_idx = self
+            .access_ticks
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, tick)| **tick)
+            .map(|(idx, _)| idx)
+            .unwrap_or(0);
+
+        let evicted = self.items.remove(evict_idx);
+        self.access_ticks.remove(evict_idx);
+
+        self.items.push((key, value));
+        self.access_ticks.push(self.next_tick);
+        self.next_tick = self.next_tick.wrapping_add(1);
+        self.item_count = self.items.len() as u16;
+        self.push_journal(JournalEntry::Insert((self.items.len() - 1) as u16));
+
+        Ok(Some(evicted))
+    }
+
     /// Convenience to check capacity.
     pub fn can_add_item(&self) -> bool {
         self.items.len() < self.max_items as usize
@@ -154,9 +385,17 @@ impl<K: ShardKey, V: ShardValue> MappingShard<K, V> {
     }
 
     /// Clear all items but maintain capacity allocation
-    pub fn clear(&mut self) {
+    pub fn clear(&mut self) -> Result<()> {
+        self.verify_integrity()?;
+        if self.readonly {
+            return err!(ShardError::ReadOnly);
+        }
+
+        self.push_journal(JournalEntry::Clear(self.items.len() as u16));
         self.items.clear();
         self.item_count = 0;
+        self.access_ticks.clear();
+        Ok(())
     }
     /// Get comprehensive capacity statistics
     pub fn capacity_stats(&self) -> CapacityStats {
@@ -179,9 +418,16 @@ where
     V: ShardValue,
 {
     fn insert(&mut self, key: K, value: V) -> Result<()> {
+        self.verify_integrity()?;
+        if self.readonly {
+            return err!(ShardError::ReadOnly);
+        }
+
         // If key exists, overwrite
         if let Some(pos) = self.items.iter().position(|(k, _)| *k == key) {
             self.items[pos].1 = value;
+            self.touch(pos);
+            self.push_journal(JournalEntry::Insert(pos as u16));
             return Ok(());
         }
 
@@ -192,10 +438,23 @@ where
 
         self.items.push((key, value));
         self.item_count = self.items.len() as u16;
+        if self.eviction_enabled {
+            self.access_ticks.push(self.next_tick);
+            self.next_tick = self.next_tick.wrapping_add(1);
+        }
+        self.push_journal(JournalEntry::Insert((self.items.len() - 1) as u16));
         Ok(())
     }
 
     fn get(&self, key: &K) -> Option<V> {
+        // LRU-blind (see `get_touch`) and corruption-blind: a corrupted
+        // shard reports no entries rather than surfacing an error, since
+        // this trait method can't return `Result`. Use `get_checked` to
+        // observe the underlying `ShardError::Corrupted` instead.
+        if self.verify_integrity().is_err() {
+            return None;
+        }
+
         self.items
             .iter()
             .find(|(k, _)| k == key)
@@ -203,9 +462,18 @@ where
     }
 
     fn remove(&mut self, key: &K) -> Result<()> {
+        self.verify_integrity()?;
+        if self.readonly {
+            return err!(ShardError::ReadOnly);
+        }
+
         if let Some(idx) = self.items.iter().position(|(k, _)| k == key) {
+            self.push_journal(JournalEntry::Remove(idx as u16));
             self.items.remove(idx);
             self.item_count = self.items.len() as u16;
+            if self.eviction_enabled {
+                self.access_ticks.remove(idx);
+            }
             Ok(())
         } else {
             err!(ShardError::KeyNotFound)
@@ -0,0 +1,49 @@
+//! Version-tagged wrapper around `MappingShard` so a future change to the
+//! on-chain layout doesn't break accounts written by older program
+//! versions: every serialized value carries a leading variant tag the
+//! deserializer can dispatch on. That guarantee only holds for layout
+//! changes that arrive as a new variant — see the note on `Legacy` below.
+
+use crate::errors::ShardError;
+use crate::shard::MappingShard;
+use crate::traits::{ShardKey, ShardValue};
+use anchor_lang::prelude::*;
+
+/// Version-tagged shard layout. `Legacy` is today's only format, and it is
+/// frozen: the versioning scheme only protects accounts that were written
+/// before a layout change if `Legacy`'s own fields never change again.
+/// Any further evolution of the on-chain layout — including adding another
+/// field to `MappingShard` — must land as a new variant here instead, even
+/// though nothing currently enforces that mechanically. Future layouts
+/// (e.g. sorted or compressed storage) can be added as new variants and
+/// will coexist with accounts already on `Legacy`, since the Borsh-encoded
+/// variant index serves as the version byte.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub enum ShardFormat<K: ShardKey, V: ShardValue> {
+    Legacy(MappingShard<K, V>),
+}
+
+impl<K: ShardKey, V: ShardValue> ShardFormat<K, V> {
+    /// Decode `data`, dispatching on its leading version byte to the
+    /// matching variant.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        Self::try_from_slice(data).map_err(|_| error!(ShardError::Corrupted))
+    }
+
+    /// Upgrade this value into the newest representation in place. A
+    /// no-op today since `Legacy` is also the newest format; once a newer
+    /// variant exists this will convert into it instead.
+    pub fn migrate(self) -> ShardFormat<K, V> {
+        match self {
+            ShardFormat::Legacy(shard) => ShardFormat::Legacy(shard),
+        }
+    }
+
+    /// Borrow the underlying shard regardless of version, for read paths
+    /// that don't need to care about the wire format.
+    pub fn shard(&self) -> &MappingShard<K, V> {
+        match self {
+            ShardFormat::Legacy(shard) => shard,
+        }
+    }
+}
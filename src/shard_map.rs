@@ -0,0 +1,145 @@
+//! Top-level router that owns a fixed, power-of-two set of shards and
+//! deterministically routes every key to exactly one of them, so on-chain
+//! programs know which single PDA to load before calling
+//! `insert`/`get`/`remove`.
+
+use crate::account_shard::derive_shard_pda;
+use crate::errors::ShardError;
+use crate::shard::MappingShard;
+use crate::traits::{ShardKey, ShardValue, ShardedMap};
+use anchor_lang::prelude::*;
+
+/// FNV-1a offset basis, per the standard 64-bit FNV-1a spec.
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+/// FNV-1a prime, per the standard 64-bit FNV-1a spec.
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Hash `bytes` with FNV-1a. Stable across runs and non-cryptographic —
+/// good enough for routing, not for anything security sensitive.
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    let mut hash = FNV_OFFSET_BASIS;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Router that owns `num_buckets` shards, always a power of two, and maps
+/// every key to exactly one of them by hashing its serialized bytes and
+/// masking with `num_buckets - 1`.
+#[derive(Clone, Debug)]
+pub struct ShardMap<K: ShardKey, V: ShardValue> {
+    shards: Vec<MappingShard<K, V>>,
+}
+
+impl<K: ShardKey, V: ShardValue> ShardMap<K, V> {
+    /// Create a new router with `num_buckets` empty shards, each able to
+    /// hold `max_items_per_shard` entries. `num_buckets` must be a power of
+    /// two so routing can use a bitmask instead of a modulo.
+    pub fn new(num_buckets: u8, max_items_per_shard: u16) -> Result<Self> {
+        if num_buckets == 0 || !num_buckets.is_power_of_two() {
+            return err!(ShardError::InvalidShard);
+        }
+
+        let shards = (0..num_buckets)
+            .map(|shard_id| MappingShard::new(shard_id, max_items_per_shard))
+            .collect();
+
+        Ok(Self { shards })
+    }
+
+    /// Number of shards this map owns. Always a power of two.
+    pub fn num_buckets_pow2(&self) -> u8 {
+        self.shards.len() as u8
+    }
+
+    /// Which shard `key` routes to: hash its serialized bytes with FNV-1a
+    /// and mask with `num_buckets - 1`. The mask is exact because
+    /// `num_buckets` is always a power of two.
+    pub fn shard_index_for(&self, key: &K) -> Result<u8> {
+        let bytes = key
+            .try_to_vec()
+            .map_err(|_| error!(ShardError::InvalidShard))?;
+        let hash = fnv1a_hash(&bytes);
+        let mask = (self.num_buckets_pow2() as u64) - 1;
+        Ok((hash & mask) as u8)
+    }
+
+    /// Derive the PDA of the shard `key` routes to, so a client can fetch
+    /// the right account before issuing `insert`/`get`/`remove`.
+    pub fn pda_for(&self, program_id: &Pubkey, key: &K) -> Result<(Pubkey, u8)> {
+        let shard_index = self.shard_index_for(key)?;
+        Ok(derive_shard_pda(program_id, shard_index))
+    }
+
+    /// Borrow the shard at `shard_index`, if it exists.
+    pub fn shard(&self, shard_index: u8) -> Option<&MappingShard<K, V>> {
+        self.shards.get(shard_index as usize)
+    }
+
+    /// Mutably borrow the shard at `shard_index`, if it exists.
+    pub fn shard_mut(&mut self, shard_index: u8) -> Option<&mut MappingShard<K, V>> {
+        self.shards.get_mut(shard_index as usize)
+    }
+
+    /// Route `key` to its shard and insert it there.
+    pub fn insert(&mut self, key: K, value: V) -> Result<()> {
+        let shard_index = self.shard_index_for(&key)?;
+        self.shards[shard_index as usize].insert(key, value)
+    }
+
+    /// Route `key` to its shard and look it up there. This is LRU-blind:
+    /// it does not bump recency, so it's unsuitable as the sole read path
+    /// for an eviction-enabled shard. Use `get_touch` there instead.
+    pub fn get(&self, key: &K) -> Result<Option<V>> {
+        let shard_index = self.shard_index_for(key)?;
+        self.shards[shard_index as usize].get_checked(key)
+    }
+
+    /// Route `key` to its shard and look it up there, bumping its recency
+    /// so it isn't picked for eviction next. This is the read path to use
+    /// for eviction-enabled shards; `get` does not update recency.
+    pub fn get_touch(&mut self, key: &K) -> Result<Option<V>> {
+        let shard_index = self.shard_index_for(key)?;
+        let shard = &mut self.shards[shard_index as usize];
+        shard.verify_integrity()?;
+        Ok(shard.get_touch(key))
+    }
+
+    /// Route `key` to its shard and remove it there.
+    pub fn remove(&mut self, key: &K) -> Result<()> {
+        let shard_index = self.shard_index_for(key)?;
+        self.shards[shard_index as usize].remove(key)
+    }
+
+    /// Grow the bucket count to any power of two no smaller than the
+    /// current one, producing a new shard set with every entry rehashed.
+    /// When the count doubles, roughly half of each old shard's entries
+    /// move to its new high sibling shard — the ones whose hash now has
+    /// the newly significant bit set. The rest stay put, since the low bits
+    /// of the hash haven't changed.
+    pub fn split_into(&self, new_num_buckets: u8) -> Result<Self> {
+        if new_num_buckets == 0
+            || !new_num_buckets.is_power_of_two()
+            || (new_num_buckets as usize) < self.shards.len()
+        {
+            return err!(ShardError::InvalidShard);
+        }
+
+        let max_items_per_shard = self
+            .shards
+            .first()
+            .map(|s| s.max_capacity() as u16)
+            .unwrap_or(0);
+
+        let mut new_map = Self::new(new_num_buckets, max_items_per_shard)?;
+        for shard in &self.shards {
+            for (key, value) in shard.items.iter() {
+                new_map.insert(key.clone(), value.clone())?;
+            }
+        }
+
+        Ok(new_map)
+    }
+}
@@ -0,0 +1,181 @@
+//! Sorted-storage variant of `MappingShard` that keeps `items` ordered by
+//! key so lookups can binary search instead of scanning linearly. This
+//! trades O(n) compute-unit cost for O(log n) comparisons plus a Vec shift
+//! on mutation — a good trade for shards that are read far more than they
+//! are written.
+
+use crate::errors::ShardError;
+use crate::traits::{ShardKey, ShardValue, ShardedMap};
+use anchor_lang::prelude::*;
+use std::marker::PhantomData;
+
+/// Keys usable with `SortedMappingShard` need a total order to binary
+/// search on, in addition to the usual `ShardKey` bounds.
+pub trait SortedShardKey: ShardKey + Ord {}
+impl<T> SortedShardKey for T where T: ShardKey + Ord {}
+
+/// Sorted counterpart to `MappingShard`: `items` is always kept ordered by
+/// key, so `insert`/`get`/`remove` use binary search instead of a linear
+/// scan. Capacity and `item_count` bookkeeping match `MappingShard`
+/// exactly.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct SortedMappingShard<K: SortedShardKey, V: ShardValue> {
+    /// Optional shard id for debugging. If you use PDAs, you can omit storing this.
+    pub shard_id: u8,
+    /// Items kept sorted ascending by key.
+    pub items: Vec<(K, V)>,
+    /// cached count (kept as u16 to reduce serialized size)
+    pub item_count: u16,
+    /// Maximum allowed items in this shard (helps sizing accounts)
+    pub max_items: u16,
+    /// Phantom type marker so we can keep K,V generic
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K: SortedShardKey, V: ShardValue> SortedMappingShard<K, V> {
+    /// Create a new, empty sorted shard.
+    pub fn new(shard_id: u8, max_items: u16) -> Self {
+        Self {
+            shard_id,
+            items: Vec::with_capacity(max_items as usize),
+            item_count: 0,
+            max_items,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Locate `key`'s index with binary search: `Ok(i)` if present at `i`,
+    /// `Err(i)` if absent (where `i` is the sorted insertion point).
+    fn search(&self, key: &K) -> std::result::Result<usize, usize> {
+        self.items.binary_search_by(|(k, _)| k.cmp(key))
+    }
+
+    /// Check if the shard is at maximum capacity.
+    pub fn is_full(&self) -> bool {
+        self.items.len() >= self.max_items as usize
+    }
+
+    /// Check if the shard contains no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Get the number of remaining slots available in this shard.
+    pub fn remaining_capacity(&self) -> usize {
+        self.max_items as usize - self.items.len()
+    }
+
+    /// Check if a batch insert would succeed without modifying the shard,
+    /// using binary search to tell new keys from updates in O(k log n).
+    pub fn can_insert_batch(&self, items: &[(K, V)]) -> bool {
+        let available_space = self.remaining_capacity();
+        let new_items_count = items
+            .iter()
+            .filter(|(key, _)| self.search(key).is_err())
+            .count();
+
+        new_items_count <= available_space
+    }
+
+    /// Calculate how many items can be added before hitting capacity.
+    pub fn space_for_new_items(&self, keys: &[K]) -> usize {
+        let new_keys_count = keys.iter().filter(|key| self.search(key).is_err()).count();
+
+        std::cmp::min(new_keys_count, self.remaining_capacity())
+    }
+
+    /// Batch insert, maintaining sort order by inserting each item at its
+    /// binary-searched position rather than appending.
+    pub fn insert_batch(&mut self, items: Vec<(K, V)>) -> Result<Vec<Result<()>>> {
+        let mut results = Vec::with_capacity(items.len());
+        for (key, value) in items {
+            results.push(self.insert(key, value));
+        }
+        Ok(results)
+    }
+
+    /// Look up `keys` in one pass by pre-sorting them and walking both the
+    /// query list and `items` together, instead of binary searching each
+    /// key independently. Results are returned in the original `keys`
+    /// order.
+    pub fn get_batch(&self, keys: &[K]) -> Vec<Option<V>> {
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+
+        let mut results = vec![None; keys.len()];
+        let mut item_idx = 0;
+        for &query_idx in &order {
+            let key = &keys[query_idx];
+            while item_idx < self.items.len() && self.items[item_idx].0 < *key {
+                item_idx += 1;
+            }
+            if item_idx < self.items.len() && self.items[item_idx].0 == *key {
+                results[query_idx] = Some(self.items[item_idx].1.clone());
+            }
+        }
+
+        results
+    }
+}
+
+impl<K, V> ShardedMap<K, V> for SortedMappingShard<K, V>
+where
+    K: SortedShardKey,
+    V: ShardValue,
+{
+    fn insert(&mut self, key: K, value: V) -> Result<()> {
+        match self.search(&key) {
+            Ok(idx) => {
+                self.items[idx].1 = value;
+                Ok(())
+            }
+            Err(idx) => {
+                if self.is_full() {
+                    return err!(ShardError::ShardFull);
+                }
+                self.items.insert(idx, (key, value));
+                self.item_count = self.items.len() as u16;
+                Ok(())
+            }
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        self.search(key).ok().map(|idx| self.items[idx].1.clone())
+    }
+
+    fn remove(&mut self, key: &K) -> Result<()> {
+        match self.search(key) {
+            Ok(idx) => {
+                self.items.remove(idx);
+                self.item_count = self.items.len() as u16;
+                Ok(())
+            }
+            Err(_) => err!(ShardError::KeyNotFound),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    fn max_capacity(&self) -> usize {
+        self.max_items as usize
+    }
+
+    fn insert_batch(&mut self, items: Vec<(K, V)>) -> Result<Vec<Result<()>>> {
+        SortedMappingShard::insert_batch(self, items)
+    }
+
+    fn get_batch(&self, keys: &[K]) -> Vec<Option<V>> {
+        SortedMappingShard::get_batch(self, keys)
+    }
+
+    fn remove_batch(&mut self, keys: &[K]) -> Result<Vec<Result<()>>> {
+        let mut results = Vec::with_capacity(keys.len());
+        for key in keys {
+            results.push(self.remove(key));
+        }
+        Ok(results)
+    }
+}